@@ -35,4 +35,6 @@ pub use statement::params::{
     ValueProjector
 };
 
-pub use implementors::GeneralMetaProvider;
\ No newline at end of file
+pub use implementors::GeneralMetaProvider;
+
+pub use values::{DbValue, ConversionError, FromSql};
\ No newline at end of file
@@ -1,13 +1,124 @@
+use std::fmt;
 use std::mem::transmute;
 use std::ptr;
+use std::str::FromStr;
 
 use crate::statement::{ParamValue, ResultValue};
 use crate::ValueProjector;
+use crate::SqlType;
+
+/// errors produced while converting between Oracle wire values and Rust
+/// types, replacing the panics/silent defaults the old transmute-based
+/// conversions used to produce on a type mismatch or a bad bind value.
+#[derive(Debug)]
+pub enum ConversionError {
+    InvalidType { expected: SqlType, found: SqlType },
+    Parse { raw: String, target: &'static str },
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConversionError::InvalidType { expected, found } =>
+                write!(f, "invalid column type: expected {:?}, found {:?}", expected, found),
+            ConversionError::Parse { raw, target } =>
+                write!(f, "can not parse '{}' as {}", raw, target),
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+/// a fallible, type-checked counterpart to the `From<&ResultValue>` impls:
+/// callers that know the column's declared `SqlType` can use this to catch a
+/// mismatch instead of silently transmuting the wrong width.
+pub trait FromSql: Sized {
+    fn from_sql(v: &ResultValue, ty: &SqlType) -> Result<Self, ConversionError>;
+}
+
+/// a neutral, typed bridge between an Oracle result column and JSON: column
+/// converters yield a `DbValue` instead of a raw `String`, so `serde_json`
+/// can serialize the row correctly (proper escaping, real `null`s) rather
+/// than hand-built `format!` concatenation.
+pub enum DbValue {
+    Null,
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+    Date(SqlDate),
+    DateTime(SqlDateTime),
+    Timestamp(chrono::NaiveDateTime),
+    TimestampTz(chrono::DateTime<chrono::FixedOffset>),
+    Decimal(rust_decimal::Decimal),
+    Blob(Vec<u8>),
+}
+
+impl DbValue {
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            DbValue::Null         => serde_json::Value::Null,
+            DbValue::Int(v)       => serde_json::Value::from(*v),
+            DbValue::Float(v)     => serde_json::Value::from(*v),
+            DbValue::Str(v)       => serde_json::Value::from(v.clone()),
+            DbValue::Bool(v)      => serde_json::Value::from(*v),
+            DbValue::Date(v)      => serde_json::Value::from(v.format("%Y-%m-%d").to_string()),
+            DbValue::DateTime(v)  => serde_json::Value::from(v.to_rfc3339()),
+            DbValue::Timestamp(v)   => serde_json::Value::from(v.format("%Y-%m-%dT%H:%M:%S%.f").to_string()),
+            DbValue::TimestampTz(v) => serde_json::Value::from(v.to_rfc3339()),
+            // rendered as an unquoted numeric literal with full precision,
+            // rather than lossily coerced through f64
+            DbValue::Decimal(v) => {
+                let text = v.to_string();
+                serde_json::Number::from_str(&text)
+                    .map(serde_json::Value::Number)
+                    .unwrap_or_else(|_| serde_json::Value::from(text))
+            },
+            // text LOBs are already folded into DbValue::Str; binary LOBs
+            // have no native JSON representation, so base64-encode them
+            DbValue::Blob(v) => serde_json::Value::from(base64::encode(v)),
+        }
+    }
+}
+
+impl ResultValue {
+    /// project this column into a neutral `DbValue` according to its
+    /// `SqlType`, so callers can serialize it with `serde_json` instead of
+    /// matching on raw strings.
+    pub fn to_db_value(&self, ty: &SqlType) -> DbValue {
+        self.try_to_db_value(ty).unwrap_or_else(|err| DbValue::Str(err.to_string()))
+    }
+
+    /// fallible counterpart of `to_db_value`. The column's bytes are read as
+    /// the Rust type that `ty` names; the wire type of the column itself is
+    /// not checked against `ty`. The one error it returns is for a NUMBER
+    /// column whose text does not parse as a `Decimal`. A NULL column
+    /// yields `DbValue::Null`.
+    pub fn try_to_db_value(&self, ty: &SqlType) -> Result<DbValue, ConversionError> {
+        let is_null = self.map(|_, _| ()).is_none();
+        match ty {
+            SqlType::Int16   => { i16::from_sql(self, ty)?; Ok(Option::<i16>::from(self).map_or(DbValue::Null, |v| DbValue::Int(v as i64))) },
+            SqlType::Int32   => { i32::from_sql(self, ty)?; Ok(Option::<i32>::from(self).map_or(DbValue::Null, |v| DbValue::Int(v as i64))) },
+            SqlType::Int64   => { i64::from_sql(self, ty)?; Ok(Option::<i64>::from(self).map_or(DbValue::Null, DbValue::Int)) },
+            SqlType::Float   => { f64::from_sql(self, ty)?; Ok(Option::<f64>::from(self).map_or(DbValue::Null, DbValue::Float)) },
+            SqlType::Varchar | SqlType::Clob | SqlType::Nclob => { String::from_sql(self, ty)?; Ok(Option::<String>::from(self).map_or(DbValue::Null, DbValue::Str)) },
+            _ if is_null      => Ok(DbValue::Null),
+            SqlType::Date     => Ok(DbValue::Date(SqlDate::from(self))),
+            SqlType::Datetime => Ok(DbValue::DateTime(SqlDateTime::from(self))),
+            SqlType::Timestamp   => { NaiveDateTime::from_sql(self, ty)?; Ok(DbValue::Timestamp(NaiveDateTime::from(self))) },
+            SqlType::TimestampTz => { DateTime::<FixedOffset>::from_sql(self, ty)?; Ok(DbValue::TimestampTz(DateTime::<FixedOffset>::from(self))) },
+            SqlType::Number      => Option::<String>::from(self)
+                .map_or(Ok(DbValue::Null), |_| Decimal::from_sql(self, ty).map(DbValue::Decimal)),
+            SqlType::Blob        => { Vec::<u8>::from_sql(self, ty)?; Ok(DbValue::Blob(Vec::<u8>::from(self))) },
+            _ => Ok(DbValue::Null),
+        }
+    }
+}
 
 // integer types, must be used only for primitive types
 
 macro_rules! convert_sql_and_primitive {
-    ($T:ty) => {
+    ($T:ty, $expected:expr) => {
 
         impl From<&ResultValue> for $T {
             fn from(v: &ResultValue) -> $T {
@@ -21,6 +132,17 @@ macro_rules! convert_sql_and_primitive {
             }
         }
 
+        // fallible path: checks the column's declared type before trusting the
+        // transmute, instead of the unconditional cast the bare `From` impl does
+        impl FromSql for $T {
+            fn from_sql(v: &ResultValue, ty: &SqlType) -> Result<$T, ConversionError> {
+                if *ty != $expected {
+                    return Err(ConversionError::InvalidType { expected: $expected, found: *ty });
+                }
+                Ok($T::from(v))
+            }
+        }
+
         impl ValueProjector<$T> for $T {
             fn project_value(&self, projection: &mut ParamValue) {
                 projection.project(self, |data, _| {
@@ -35,16 +157,16 @@ macro_rules! convert_sql_and_primitive {
     }
 }
 
-convert_sql_and_primitive!(i16);
-convert_sql_and_primitive!(u16);
+convert_sql_and_primitive!(i16, SqlType::Int16);
+convert_sql_and_primitive!(u16, SqlType::Int16);
 
-convert_sql_and_primitive!(i32);
-convert_sql_and_primitive!(u32);
+convert_sql_and_primitive!(i32, SqlType::Int32);
+convert_sql_and_primitive!(u32, SqlType::Int32);
 
-convert_sql_and_primitive!(i64);
-convert_sql_and_primitive!(u64);
+convert_sql_and_primitive!(i64, SqlType::Int64);
+convert_sql_and_primitive!(u64, SqlType::Int64);
 
-convert_sql_and_primitive!(f64);
+convert_sql_and_primitive!(f64, SqlType::Float);
 
 
 // String type, in Oracle NULL String is Empty String
@@ -63,6 +185,30 @@ impl From<&ResultValue> for String {
     }
 }
 
+impl From<&ResultValue> for Option<String> {
+    fn from(v: &ResultValue) -> Option<String> {
+        v.map(|valp,len| {
+            let str_len = len as usize;
+            let mut dst = Vec::with_capacity(str_len) as Vec<u8>;
+            unsafe {
+                dst.set_len(str_len);
+                ptr::copy(valp, dst.as_mut_ptr(), str_len);
+                String::from_utf8_unchecked(dst)
+            }
+        })
+    }
+}
+
+impl FromSql for String {
+    fn from_sql(v: &ResultValue, ty: &SqlType) -> Result<String, ConversionError> {
+        // CLOB/NCLOB locators are streamed into the same owned String as VARCHAR
+        match ty {
+            SqlType::Varchar | SqlType::Clob | SqlType::Nclob => Ok(String::from(v)),
+            other => Err(ConversionError::InvalidType { expected: SqlType::Varchar, found: *other }),
+        }
+    }
+}
+
 impl ValueProjector<String> for String {
     fn project_value(&self, projection: &mut ParamValue) {
         projection.project(self, |data, indp| {
@@ -120,9 +266,6 @@ impl ValueProjector<bool> for bool {
 use chrono::prelude::*;
 use crate::sql_types::*;
 
-// TODO: Datetime have 7 bytes
-// TODO: Timestamp have 11 bytes
-
 impl From<&ResultValue> for SqlDate {
     fn from(v: &ResultValue) -> SqlDate {
         v.map_or(Local::now().date(),|valp,len| {
@@ -178,4 +321,255 @@ impl ValueProjector<SqlDate> for SqlDate {
     }
 }
 
+// TIMESTAMP and TIMESTAMP WITH TIME ZONE
+//
+// layout shares the first 7 bytes with DATE (century+100, year+100, month,
+// day, hour+1, minute+1, second+1); TIMESTAMP appends a big-endian u32
+// nanosecond count in bytes 7..11, and TIMESTAMP WITH TIME ZONE appends two
+// further bytes [tz_hour+20, tz_minute+60].
+
+fn read_nanos(vec: &[u8]) -> u32 {
+    ((vec[7] as u32) << 24) | ((vec[8] as u32) << 16) | ((vec[9] as u32) << 8) | vec[10] as u32
+}
+
+fn write_nanos(data: *mut u8, nanos: u32) {
+    unsafe {
+        *data.offset(7)  = (nanos >> 24) as u8;
+        *data.offset(8)  = (nanos >> 16) as u8;
+        *data.offset(9)  = (nanos >> 8) as u8;
+        *data.offset(10) = nanos as u8;
+    }
+}
+
+/// decode the [tz_hour+20, tz_minute+60] trailer of a TIMESTAMP WITH TIME
+/// ZONE into a signed UTC offset in seconds. `tz_minute` carries its own
+/// sign independently of `tz_hour`, so an offset whose hour component is
+/// zero (e.g. -00:30) still round-trips correctly.
+fn decode_tz_offset_secs(tz_hour_byte: u8, tz_minute_byte: u8) -> i32 {
+    let tz_hour = tz_hour_byte as i32 - 20;
+    let tz_minute = tz_minute_byte as i32 - 60;
+    tz_hour * 3600 + tz_minute * 60
+}
+
+/// the inverse of `decode_tz_offset_secs`: split a signed UTC offset in
+/// seconds into the `[tz_hour+20, tz_minute+60]` trailer bytes.
+fn encode_tz_offset_secs(offset_secs: i32) -> (u8, u8) {
+    let tz_hour = offset_secs / 3600;
+    let tz_minute = (offset_secs % 3600) / 60;
+    ((tz_hour + 20) as u8, (tz_minute + 60) as u8)
+}
+
+impl From<&ResultValue> for NaiveDateTime {
+    fn from(v: &ResultValue) -> NaiveDateTime {
+        v.map_or(Local::now().naive_local(),|valp,len| {
+            assert!(len >= 11, "Oracle Timestamp length must be at least 11 bytes");
+            let vec = unsafe { std::slice::from_raw_parts(valp, len as usize) };
+
+            let y = (vec[0] as i32 - 100)*100 + vec[1] as i32 - 100;
+            let m = vec[2] as u32;
+            let d = vec[3] as u32;
+
+            let hh = vec[4] as u32 - 1;
+            let mm = vec[5] as u32 - 1;
+            let ss = vec[6] as u32 - 1;
+
+            NaiveDate::from_ymd(y,m,d).and_hms_nano(hh,mm,ss, read_nanos(vec))
+        })
+    }
+}
+
+impl ValueProjector<NaiveDateTime> for NaiveDateTime {
+    fn project_value(&self, projection: &mut ParamValue) {
+        projection.project(self, |data, _| {
+            let century = (self.year() / 100 + 100) as u8;
+            let year = (self.year() % 100 + 100) as u8;
+            let month = self.month() as u8;
+            let day = self.day() as u8;
+            unsafe {
+                *data = century;
+                *data.offset(1) = year;
+                *data.offset(2) = month;
+                *data.offset(3) = day;
+                *data.offset(4) = (self.hour() + 1) as u8;
+                *data.offset(5) = (self.minute() + 1) as u8;
+                *data.offset(6) = (self.second() + 1) as u8;
+            }
+            write_nanos(data, self.nanosecond());
+            0
+        });
+    }
+}
+
+impl FromSql for NaiveDateTime {
+    fn from_sql(v: &ResultValue, ty: &SqlType) -> Result<NaiveDateTime, ConversionError> {
+        if *ty != SqlType::Timestamp {
+            return Err(ConversionError::InvalidType { expected: SqlType::Timestamp, found: *ty });
+        }
+        Ok(NaiveDateTime::from(v))
+    }
+}
+
+impl From<&ResultValue> for DateTime<FixedOffset> {
+    fn from(v: &ResultValue) -> DateTime<FixedOffset> {
+        v.map_or_else(|| Local::now().into(),|valp,len| {
+            assert!(len >= 13, "Oracle Timestamp With Time Zone length must be at least 13 bytes");
+            let vec = unsafe { std::slice::from_raw_parts(valp, len as usize) };
+
+            let y = (vec[0] as i32 - 100)*100 + vec[1] as i32 - 100;
+            let m = vec[2] as u32;
+            let d = vec[3] as u32;
+
+            let hh = vec[4] as u32 - 1;
+            let mm = vec[5] as u32 - 1;
+            let ss = vec[6] as u32 - 1;
+
+            let offset_secs = decode_tz_offset_secs(vec[11], vec[12]);
+
+            let offset = FixedOffset::east_opt(offset_secs).unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+            let naive = NaiveDate::from_ymd(y,m,d).and_hms_nano(hh,mm,ss, read_nanos(vec));
+
+            offset.from_local_datetime(&naive).single().unwrap_or_else(|| offset.from_utc_datetime(&naive))
+        })
+    }
+}
+
+impl ValueProjector<DateTime<FixedOffset>> for DateTime<FixedOffset> {
+    fn project_value(&self, projection: &mut ParamValue) {
+        projection.project(self, |data, _| {
+            let century = (self.year() / 100 + 100) as u8;
+            let year = (self.year() % 100 + 100) as u8;
+            let month = self.month() as u8;
+            let day = self.day() as u8;
+            let (tz_hour_byte, tz_minute_byte) = encode_tz_offset_secs(self.offset().local_minus_utc());
+            unsafe {
+                *data = century;
+                *data.offset(1) = year;
+                *data.offset(2) = month;
+                *data.offset(3) = day;
+                *data.offset(4) = (self.hour() + 1) as u8;
+                *data.offset(5) = (self.minute() + 1) as u8;
+                *data.offset(6) = (self.second() + 1) as u8;
+                *data.offset(11) = tz_hour_byte;
+                *data.offset(12) = tz_minute_byte;
+            }
+            write_nanos(data, self.nanosecond());
+            0
+        });
+    }
+}
+
+impl FromSql for DateTime<FixedOffset> {
+    fn from_sql(v: &ResultValue, ty: &SqlType) -> Result<DateTime<FixedOffset>, ConversionError> {
+        if *ty != SqlType::TimestampTz {
+            return Err(ConversionError::InvalidType { expected: SqlType::TimestampTz, found: *ty });
+        }
+        Ok(DateTime::<FixedOffset>::from(v))
+    }
+}
+
+// NUMBER, bound/fetched as text via the OCI descriptor rather than coerced
+// through f64, so arbitrary precision survives the round trip
+
+use rust_decimal::Decimal;
+
+// no infallible `From<&ResultValue> for Decimal`: unlike the other primitive
+// conversions, a NUMBER's text representation can fail to parse, and
+// defaulting that to zero would hide a financial value silently turning
+// into 0 instead of surfacing the bad column. try_to_db_value uses
+// `FromSql::from_sql` below and reports a parse failure as a real error.
+impl FromSql for Decimal {
+    fn from_sql(v: &ResultValue, ty: &SqlType) -> Result<Decimal, ConversionError> {
+        if *ty != SqlType::Number {
+            return Err(ConversionError::InvalidType { expected: SqlType::Number, found: *ty });
+        }
+        let s = String::from(v);
+        s.parse().map_err(|_| ConversionError::Parse { raw: s, target: "Number" })
+    }
+}
+
+impl ValueProjector<Decimal> for Decimal {
+    fn project_value(&self, projection: &mut ParamValue) {
+        self.to_string().project_value(projection);
+    }
+}
+
+// BLOB, streamed into an owned byte buffer; CLOB/NCLOB reuse the String
+// conversions above (same locator streaming, text rather than binary)
+
+impl From<&ResultValue> for Vec<u8> {
+    fn from(v: &ResultValue) -> Vec<u8> {
+        v.map_or(Vec::new(),|valp,len| {
+            let len = len as usize;
+            let mut dst = Vec::with_capacity(len);
+            unsafe {
+                dst.set_len(len);
+                ptr::copy(valp, dst.as_mut_ptr(), len);
+            }
+            dst
+        })
+    }
+}
+
+impl ValueProjector<Vec<u8>> for Vec<u8> {
+    fn project_value(&self, projection: &mut ParamValue) {
+        projection.project(self, |data, _| {
+            unsafe { ptr::copy(self.as_ptr(), data, self.len()) };
+            self.len()
+        });
+    }
+}
+
+impl FromSql for Vec<u8> {
+    fn from_sql(v: &ResultValue, ty: &SqlType) -> Result<Vec<u8>, ConversionError> {
+        if *ty != SqlType::Blob {
+            return Err(ConversionError::InvalidType { expected: SqlType::Blob, found: *ty });
+        }
+        Ok(Vec::<u8>::from(v))
+    }
+}
+
 // TODO: optional converters for date and datetime
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nanos_round_trip() {
+        let mut buf = [0u8; 11];
+        write_nanos(buf.as_mut_ptr(), 123_456_789);
+        assert_eq!(read_nanos(&buf), 123_456_789);
+    }
+
+    #[test]
+    fn tz_offset_round_trip_positive_minutes_only() {
+        let (h, m) = encode_tz_offset_secs(30 * 60);
+        assert_eq!(decode_tz_offset_secs(h, m), 30 * 60);
+    }
+
+    #[test]
+    fn tz_offset_round_trip_negative_minutes_only() {
+        let (h, m) = encode_tz_offset_secs(-30 * 60);
+        assert_eq!(decode_tz_offset_secs(h, m), -30 * 60);
+    }
+
+    #[test]
+    fn tz_offset_round_trip_positive_hour_and_minutes() {
+        let secs = 5 * 3600 + 45 * 60;
+        let (h, m) = encode_tz_offset_secs(secs);
+        assert_eq!(decode_tz_offset_secs(h, m), secs);
+    }
+
+    #[test]
+    fn tz_offset_round_trip_negative_hour_and_minutes() {
+        let secs = -(5 * 3600 + 45 * 60);
+        let (h, m) = encode_tz_offset_secs(secs);
+        assert_eq!(decode_tz_offset_secs(h, m), secs);
+    }
+
+    #[test]
+    fn tz_offset_round_trip_zero() {
+        let (h, m) = encode_tz_offset_secs(0);
+        assert_eq!(decode_tz_offset_secs(h, m), 0);
+    }
+}
@@ -8,7 +8,11 @@ pub struct DynamicQuery {
 
     param_columns:      Vec<ColTypeInfo>,
     param_column_names: Vec<String>,
-    parsed_params:      Vec<ParsedParameter>
+    param_ops:          Vec<ComparisonOp>,
+    parsed_params:      Vec<ParsedParameter>,
+
+    order_by: Vec<OrderBy>,
+    limit:    Option<Limit>,
 }
 
 struct DynamicResultsProvider {
@@ -21,7 +25,7 @@ struct DynamicParamsProvider {
 }
 
 enum ParsedParameter {
-    Int16 (i16), Int32(i32), Int64(i64), Varchar(String)
+    Int16 (i16), Int32(i32), Int64(i64), Varchar(String), Decimal(rust_decimal::Decimal), Blob(Vec<u8>)
 }
 
 struct ColTypeInfo {
@@ -35,76 +39,243 @@ impl ColTypeInfo {
     }
 }
 
+/// a single WHERE predicate, as supplied by the caller: a comparison together
+/// with its raw (unparsed) value(s). Values are parsed against the target
+/// column's `SqlType` once the column is resolved in `create_from_params`.
+pub enum Comparison {
+    Eq(String),
+    Ne(String),
+    Lt(String),
+    Le(String),
+    Gt(String),
+    Ge(String),
+    Like(String),
+    IsNull,
+    In(Vec<String>)
+}
+
+/// the parsed, column-agnostic shape of a `Comparison`, kept alongside each
+/// param column for SQL generation. Carries only what's needed to render the
+/// operator and count bind placeholders; the actual bound values live in
+/// `DynamicQuery::parsed_params`.
+enum ComparisonOp {
+    Eq, Ne, Lt, Le, Gt, Ge, Like, IsNull, In(usize)
+}
+
+impl ComparisonOp {
+    fn sql_operator(&self) -> &'static str {
+        match self {
+            ComparisonOp::Eq   => "=",
+            ComparisonOp::Ne   => "!=",
+            ComparisonOp::Lt   => "<",
+            ComparisonOp::Le   => "<=",
+            ComparisonOp::Gt   => ">",
+            ComparisonOp::Ge   => ">=",
+            ComparisonOp::Like => "LIKE",
+            ComparisonOp::IsNull | ComparisonOp::In(_) => "",
+        }
+    }
+}
+
+pub enum Direction { Asc, Desc }
+
+pub struct OrderBy {
+    pub column:    String,
+    pub direction: Direction,
+}
+
+pub struct Limit {
+    pub limit:  u32,
+    pub offset: u32,
+}
+
 impl DynamicQuery {
-    pub fn create_from_pk(schema_name: &str, table_info: &mi::TableInfo, parameter: String) -> Result<DynamicQuery, &'static str> {
-        match &table_info.primary_key {
-            None => Err("Primary key not exists"),
-            Some(pk) => {
-                let pk_indices = &pk.column_indices;
-                if pk_indices.len() > 1 {
-                    return Err("Primary key must have only ONE column")
-                }
-                let pk_column_index = unsafe { pk_indices.get_unchecked(0) };
-                let pk_column = unsafe { table_info.columns.get_unchecked(*pk_column_index) };
+    /// fetch a row by its primary key, which may span several columns.
+    /// `parameters` must supply exactly one value per `pk.column_indices`,
+    /// in that order. `select` optionally restricts the projected columns to
+    /// a subset of `table_info.columns`; `None` projects all of them.
+    pub fn create_from_pk(
+        schema_name: &str,
+        table_info:  &mi::TableInfo,
+        parameters:  Vec<String>,
+        select:      Option<Vec<String>>,
+    ) -> Result<DynamicQuery, String> {
+        let pk = table_info.primary_key.as_ref().ok_or_else(|| "Primary key not exists".to_string())?;
+
+        if parameters.len() != pk.column_indices.len() {
+            return Err(format!("Primary key has {} column(s), but {} parameter(s) were given", pk.column_indices.len(), parameters.len()));
+        }
 
-                let columns: Vec<ColTypeInfo> = table_info.columns.iter().map(ColTypeInfo::new).collect();
-                let column_names: Vec<&str> = table_info.columns.iter().map(|c|c.name.as_str()).collect();
+        let (columns, column_names) = Self::select_columns(table_info, select)?;
 
-                let param_column_names = vec![pk_column.name.clone()];
-                let pk_column = ColTypeInfo::new( pk_column );
+        let pk_len = pk.column_indices.len();
+        let mut param_columns = Vec::with_capacity(pk_len);
+        let mut param_column_names = Vec::with_capacity(pk_len);
+        let mut param_ops = Vec::with_capacity(pk_len);
+        let mut parsed_params = Vec::with_capacity(pk_len);
 
-                let table_name = format!("{}.{}", schema_name, table_info.name.as_str());
-                let column_names = column_names.iter().map(|name|name.to_string()).collect();
+        for (&col_idx, value) in pk.column_indices.iter().zip(parameters) {
+            let column = &table_info.columns[col_idx];
+            let parsed_parameter = ParsedParameter::parse(column.col_type, value)
+                .map_err(|err| err.to_string())?;
 
-                ParsedParameter::parse(pk_column.col_type, parameter)
-                    .map(|parsed_parameter|DynamicQuery{table_name, columns, column_names, param_columns: vec![pk_column], param_column_names, parsed_params: vec![parsed_parameter]})
+            param_columns.push(ColTypeInfo::new(column));
+            param_column_names.push(column.name.clone());
+            param_ops.push(ComparisonOp::Eq);
+            parsed_params.push(parsed_parameter);
+        }
+
+        let table_name = format!("{}.{}", schema_name, table_info.name.as_str());
+
+        Ok(DynamicQuery {
+            table_name, columns, column_names,
+            param_columns, param_column_names, param_ops, parsed_params,
+            order_by: Vec::new(),
+            limit: None,
+        })
+    }
+
+    /// resolve the columns to project: all of `table_info.columns` when
+    /// `select` is `None`, otherwise just the named subset, validated against
+    /// the table's schema.
+    fn select_columns(table_info: &mi::TableInfo, select: Option<Vec<String>>) -> Result<(Vec<ColTypeInfo>, Vec<String>), String> {
+        match select {
+            None => {
+                let columns = table_info.columns.iter().map(ColTypeInfo::new).collect();
+                let column_names = table_info.columns.iter().map(|c| c.name.clone()).collect();
+                Ok((columns, column_names))
+            },
+            Some(names) => {
+                let mut columns = Vec::with_capacity(names.len());
+                let mut column_names = Vec::with_capacity(names.len());
+                for name in names {
+                    let column = table_info.columns.iter().find(|c| c.name == name)
+                        .ok_or_else(|| format!("Not found column {}", name))?;
+                    columns.push(ColTypeInfo::new(column));
+                    column_names.push(column.name.clone());
+                }
+                Ok((columns, column_names))
             }
         }
     }
 
-    pub fn create_from_params(schema_name: &str, table_info: &mi::TableInfo, parameters: Vec<(String,String)>) -> Result<DynamicQuery, String> {
+    /// build a query from a set of column predicates, an optional ordering and
+    /// an optional page (`LIMIT`/`OFFSET`). Predicate values are parsed against
+    /// the matching column's `SqlType` and flattened into `parsed_params` in
+    /// the exact order their `:n` placeholders are emitted by `generate_sql`,
+    /// including one entry per `IN` list element.
+    pub fn create_from_params(
+        schema_name: &str,
+        table_info:  &mi::TableInfo,
+        predicates:  Vec<(String, Comparison)>,
+        order_by:    Vec<OrderBy>,
+        limit:       Option<Limit>,
+    ) -> Result<DynamicQuery, String> {
         let columns: Vec<ColTypeInfo> = table_info.columns.iter().map(ColTypeInfo::new).collect();
         let column_names: Vec<&str> = table_info.columns.iter().map(|c|c.name.as_str()).collect();
 
-        let param_columns_len = parameters.len();
+        let predicates_len = predicates.len();
 
-        let mut param_column_names = Vec::with_capacity(param_columns_len);
-        let mut param_columns = Vec::with_capacity(param_columns_len);
-        let mut parsed_params = Vec::with_capacity(param_columns_len);
+        let mut param_column_names = Vec::with_capacity(predicates_len);
+        let mut param_columns = Vec::with_capacity(predicates_len);
+        let mut param_ops = Vec::with_capacity(predicates_len);
+        let mut parsed_params = Vec::with_capacity(predicates_len);
 
-        for (ref col_name,ref p) in parameters {
-            let column = table_info.columns.iter().find(|c|&c.name == col_name);
+        for (col_name, comparison) in predicates {
+            let column = table_info.columns.iter().find(|c| c.name == col_name);
 
-            match column {
+            let column = match column {
                 None => return Err(format!("Not found column {}", col_name)),
-                Some(column) => {
-                    let parsed = ParsedParameter::parse(column.col_type, p.to_string());
-                    match parsed {
-                        Err(err) => return Err(format!("Can not parse parameter value {} for column {}: {}", p, col_name, err)),
-                        Ok(parsed) => {
-                            parsed_params.push(parsed);
-                            param_columns.push(ColTypeInfo::new( column ));
-                            param_column_names.push(col_name.to_owned());
-                        }
+                Some(column) => column,
+            };
+
+            let bound_before = parsed_params.len();
+            let op = match comparison {
+                Comparison::Eq(v)   => { parsed_params.push(parse_value(column, &col_name, v)?); ComparisonOp::Eq },
+                Comparison::Ne(v)   => { parsed_params.push(parse_value(column, &col_name, v)?); ComparisonOp::Ne },
+                Comparison::Lt(v)   => { parsed_params.push(parse_value(column, &col_name, v)?); ComparisonOp::Lt },
+                Comparison::Le(v)   => { parsed_params.push(parse_value(column, &col_name, v)?); ComparisonOp::Le },
+                Comparison::Gt(v)   => { parsed_params.push(parse_value(column, &col_name, v)?); ComparisonOp::Gt },
+                Comparison::Ge(v)   => { parsed_params.push(parse_value(column, &col_name, v)?); ComparisonOp::Ge },
+                Comparison::Like(v) => { parsed_params.push(parse_value(column, &col_name, v)?); ComparisonOp::Like },
+                Comparison::IsNull  => ComparisonOp::IsNull,
+                Comparison::In(values) => {
+                    if values.is_empty() {
+                        return Err(format!("In predicate for column {} requires at least one value", col_name));
                     }
-                }
+                    let n = values.len();
+                    for v in values {
+                        parsed_params.push(parse_value(column, &col_name, v)?);
+                    }
+                    ComparisonOp::In(n)
+                },
+            };
+
+            // one bind descriptor per value this predicate bound, so descriptors
+            // stay aligned with `parsed_params` (none for IS NULL, n for IN)
+            for _ in bound_before..parsed_params.len() {
+                param_columns.push(ColTypeInfo::new(column));
+            }
+            param_column_names.push(col_name);
+            param_ops.push(op);
+        }
+
+        for o in &order_by {
+            if !table_info.columns.iter().any(|c| c.name == o.column) {
+                return Err(format!("Not found column {}", o.column));
             }
         }
 
+        if let Some(l) = &limit {
+            parsed_params.push(ParsedParameter::Int64(l.offset as i64));
+            parsed_params.push(ParsedParameter::Int64(l.limit as i64));
+        }
+
         let table_name = format!("{}.{}", schema_name, table_info.name.as_str());
         let column_names = column_names.iter().map(|name|name.to_string()).collect();
 
-        Ok( DynamicQuery { table_name, columns, column_names, param_columns, param_column_names, parsed_params } )
+        Ok( DynamicQuery { table_name, columns, column_names, param_columns, param_column_names, param_ops, parsed_params, order_by, limit } )
     }
 
     fn generate_sql(&self) -> String {
         let joined_result_columns = self.column_names.join(",");
 
-        let enumerated_param_columns: Vec<String> =
-            self.param_column_names.iter().enumerate().map(|(idx,name)|format!("{} = :{}", name, idx+1)).collect();
-        let joined_param_columns = enumerated_param_columns.join(" AND ");
+        let mut idx = 0usize;
+        let predicate_clauses: Vec<String> = self.param_column_names.iter().zip(self.param_ops.iter())
+            .map(|(name, op)| match op {
+                ComparisonOp::IsNull => format!("{} IS NULL", name),
+                ComparisonOp::In(n) => {
+                    let placeholders: Vec<String> = (0..*n).map(|_| { idx += 1; format!(":{}", idx) }).collect();
+                    format!("{} IN ({})", name, placeholders.join(","))
+                },
+                _ => {
+                    idx += 1;
+                    format!("{} {} :{}", name, op.sql_operator(), idx)
+                }
+            }).collect();
+
+        let mut sql = format!("SELECT {} FROM {}", joined_result_columns, self.table_name);
+
+        if !predicate_clauses.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&predicate_clauses.join(" AND "));
+        }
+
+        if !self.order_by.is_empty() {
+            let order_clauses: Vec<String> = self.order_by.iter()
+                .map(|o| format!("{} {}", o.column, match o.direction { Direction::Asc => "ASC", Direction::Desc => "DESC" }))
+                .collect();
+            sql.push_str(" ORDER BY ");
+            sql.push_str(&order_clauses.join(", "));
+        }
+
+        if self.limit.is_some() {
+            let offset_idx = idx + 1;
+            let fetch_idx = idx + 2;
+            sql.push_str(&format!(" OFFSET :{} ROWS FETCH NEXT :{} ROWS ONLY", offset_idx, fetch_idx));
+        }
 
-        format!("SELECT {} FROM {} WHERE {}", joined_result_columns, self.table_name, joined_param_columns)
+        sql
     }
 
     /// execute a query and generate JSON result
@@ -149,25 +320,41 @@ impl DynamicQuery {
 
 }
 
+/// parse a single predicate value against `column`'s `SqlType`, wrapping any
+/// error with the column name so callers get a precise message.
+fn parse_value(column: &mi::ColumnInfo, col_name: &str, value: String) -> Result<ParsedParameter, String> {
+    ParsedParameter::parse(column.col_type, value.clone())
+        .map_err(|err| format!("Can not parse parameter value {} for column {}: {}", value, col_name, err))
+}
+
 impl ParsedParameter {
-    fn parse(tp: oracle::SqlType, value: String) -> Result<Self, &'static str> {
+    fn parse(tp: oracle::SqlType, value: String) -> Result<Self, oracle::ConversionError> {
         match tp {
             oracle::SqlType::Int16 => {
-                let val: i16 = value.parse().unwrap();
+                let val: i16 = value.parse().map_err(|_| oracle::ConversionError::Parse { raw: value.clone(), target: "Int16" })?;
                 Ok(ParsedParameter::Int16(val))
             },
             oracle::SqlType::Int32 => {
-                let val: i32 = value.parse().unwrap();
+                let val: i32 = value.parse().map_err(|_| oracle::ConversionError::Parse { raw: value.clone(), target: "Int32" })?;
                 Ok(ParsedParameter::Int32(val))
             },
             oracle::SqlType::Int64 => {
-                let val: i64 = value.parse().unwrap();
+                let val: i64 = value.parse().map_err(|_| oracle::ConversionError::Parse { raw: value.clone(), target: "Int64" })?;
                 Ok(ParsedParameter::Int64(val))
             },
-            oracle::SqlType::Varchar => {
+            oracle::SqlType::Varchar | oracle::SqlType::Clob | oracle::SqlType::Nclob => {
                 Ok(ParsedParameter::Varchar(value))
             },
-            _ => Err("Not supported type for Primary key")
+            oracle::SqlType::Number => {
+                let val: rust_decimal::Decimal = value.parse().map_err(|_| oracle::ConversionError::Parse { raw: value.clone(), target: "Number" })?;
+                Ok(ParsedParameter::Decimal(val))
+            },
+            // no text-friendly parameter API for binary LOBs yet, so a bound
+            // value is taken as raw bytes of the supplied string
+            oracle::SqlType::Blob => {
+                Ok(ParsedParameter::Blob(value.into_bytes()))
+            },
+            other => Err(oracle::ConversionError::InvalidType { expected: oracle::SqlType::Varchar, found: other })
         }
     }
 
@@ -185,6 +372,12 @@ impl ParsedParameter {
             Self::Varchar(val) => {
                 val.project_value(p);
             },
+            Self::Decimal(val) => {
+                val.project_value(p);
+            },
+            Self::Blob(val) => {
+                val.project_value(p);
+            },
         };
     }
 }
@@ -200,8 +393,8 @@ impl oracle::ResultsProvider<String> for DynamicResultsProvider {
             .zip(self.column_names.iter())
             .zip(rs.iter())
             .map(|((c, name), value)|{
-                let result = value.to_owned().try_to_string(&c.col_type).unwrap_or_else(|err| err.to_string());
-                format!("\"{}\":{}", name, result)
+                let db_value = value.to_owned().to_db_value(&c.col_type);
+                format!("{}:{}", serde_json::to_string(name).unwrap_or_default(), db_value.to_json())
             }).collect();
 
         format!("{{ {} }}", results.join(","))
@@ -224,3 +417,69 @@ impl oracle::ParamsProvider<Vec<ParsedParameter>> for DynamicParamsProvider {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(param_column_names: Vec<&str>, param_ops: Vec<ComparisonOp>, order_by: Vec<OrderBy>, limit: Option<Limit>) -> DynamicQuery {
+        DynamicQuery {
+            table_name: "SCHEMA.TABLE".to_string(),
+            columns: Vec::new(),
+            column_names: vec!["ID".to_string(), "NAME".to_string()],
+            param_columns: Vec::new(),
+            param_column_names: param_column_names.into_iter().map(|s| s.to_string()).collect(),
+            param_ops,
+            parsed_params: Vec::new(),
+            order_by,
+            limit,
+        }
+    }
+
+    #[test]
+    fn generate_sql_no_predicates() {
+        let q = query(vec![], vec![], vec![], None);
+        assert_eq!(q.generate_sql(), "SELECT ID,NAME FROM SCHEMA.TABLE");
+    }
+
+    #[test]
+    fn generate_sql_predicates_and_is_null() {
+        let q = query(vec!["ID", "NAME"], vec![ComparisonOp::Eq, ComparisonOp::IsNull], vec![], None);
+        assert_eq!(q.generate_sql(), "SELECT ID,NAME FROM SCHEMA.TABLE WHERE ID = :1 AND NAME IS NULL");
+    }
+
+    #[test]
+    fn generate_sql_in_list_placeholders() {
+        let q = query(vec!["ID"], vec![ComparisonOp::In(3)], vec![], None);
+        assert_eq!(q.generate_sql(), "SELECT ID,NAME FROM SCHEMA.TABLE WHERE ID IN (:1,:2,:3)");
+    }
+
+    #[test]
+    fn generate_sql_order_by() {
+        let q = query(vec![], vec![], vec![
+            OrderBy { column: "NAME".to_string(), direction: Direction::Asc },
+            OrderBy { column: "ID".to_string(), direction: Direction::Desc },
+        ], None);
+        assert_eq!(q.generate_sql(), "SELECT ID,NAME FROM SCHEMA.TABLE ORDER BY NAME ASC, ID DESC");
+    }
+
+    #[test]
+    fn generate_sql_limit_placeholders_follow_predicates() {
+        let q = query(vec!["ID"], vec![ComparisonOp::Eq], vec![], Some(Limit { limit: 10, offset: 20 }));
+        assert_eq!(q.generate_sql(), "SELECT ID,NAME FROM SCHEMA.TABLE WHERE ID = :1 OFFSET :2 ROWS FETCH NEXT :3 ROWS ONLY");
+    }
+
+    #[test]
+    fn generate_sql_predicates_order_and_limit_combined() {
+        let q = query(
+            vec!["ID"],
+            vec![ComparisonOp::In(2)],
+            vec![OrderBy { column: "NAME".to_string(), direction: Direction::Desc }],
+            Some(Limit { limit: 50, offset: 0 }),
+        );
+        assert_eq!(
+            q.generate_sql(),
+            "SELECT ID,NAME FROM SCHEMA.TABLE WHERE ID IN (:1,:2) ORDER BY NAME DESC OFFSET :3 ROWS FETCH NEXT :4 ROWS ONLY"
+        );
+    }
+}